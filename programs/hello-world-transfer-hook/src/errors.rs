@@ -0,0 +1,25 @@
+use anchor_lang::prelude::*;
+
+#[error_code]
+pub enum TransferHookError {
+    #[msg("Mint transfer hook does not point to this program")]
+    WrongHookProgram,
+    #[msg("Transfer hook must be invoked through a CPI from the token program")]
+    NotInvokedViaCpi,
+    #[msg("Source or destination token account is frozen")]
+    AccountFrozen,
+    #[msg("Signer is not the ExtraAccountMetaList authority")]
+    Unauthorized,
+    #[msg("ExtraAccountMetaList account is not a valid list owned by this program")]
+    InvalidExtraAccountMetaList,
+    #[msg("Source token account has no TransferHookAccount extension, check the mint's TransferHook extension")]
+    MissingTransferHookExtension,
+    #[msg("Signer is neither the mint authority nor the transfer hook authority")]
+    NotSetupAuthority,
+    #[msg("Signer is not the pending ExtraAccountMetaList authority")]
+    NotPendingAuthority,
+    #[msg("ExtraAccountMetaList authority can't be the default public key")]
+    InvalidNewAuthority,
+    #[msg("Source token account is not in the middle of a transfer")]
+    NotTransferring,
+}
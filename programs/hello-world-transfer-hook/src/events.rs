@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::TransferHookError;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReasonCode {
+    Approved,
+    WrongHookProgram,
+    NotInvokedViaCpi,
+    AccountFrozen,
+    InvalidExtraAccountMetaList,
+    MissingTransferHookExtension,
+    NotTransferring,
+    Other,
+}
+
+impl From<&Result<()>> for ReasonCode {
+    fn from(result: &Result<()>) -> Self {
+        match result {
+            Ok(()) => ReasonCode::Approved,
+            Err(err) if *err == TransferHookError::WrongHookProgram.into() => {
+                ReasonCode::WrongHookProgram
+            }
+            Err(err) if *err == TransferHookError::NotInvokedViaCpi.into() => {
+                ReasonCode::NotInvokedViaCpi
+            }
+            Err(err) if *err == TransferHookError::AccountFrozen.into() => {
+                ReasonCode::AccountFrozen
+            }
+            Err(err) if *err == TransferHookError::InvalidExtraAccountMetaList.into() => {
+                ReasonCode::InvalidExtraAccountMetaList
+            }
+            Err(err) if *err == TransferHookError::MissingTransferHookExtension.into() => {
+                ReasonCode::MissingTransferHookExtension
+            }
+            Err(err) if *err == TransferHookError::NotTransferring.into() => {
+                ReasonCode::NotTransferring
+            }
+            Err(_) => ReasonCode::Other,
+        }
+    }
+}
+
+// Returned by simulate_transfer so clients can preflight the hook decision
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransferDecision {
+    pub approved: bool,
+    pub reason_code: ReasonCode,
+}
+
+// Emitted by transfer_hook for every transfer it evaluates, approved or rejected
+#[event]
+pub struct TransferEvaluated {
+    pub source_owner: Pubkey,
+    pub destination_owner: Pubkey,
+    pub amount: u64,
+    pub approved: bool,
+    pub reason_code: ReasonCode,
+}
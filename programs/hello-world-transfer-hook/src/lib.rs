@@ -1,18 +1,56 @@
+#![allow(unexpected_cfgs)]
+// #[program] generates __private::__idl::__idl_resize_account at the crate root, which
+// still calls the deprecated AccountInfo::realloc, so this can't be scoped to an item
+#![allow(deprecated)]
+
 use anchor_lang::{
     prelude::*,
-    system_program::{create_account, CreateAccount},
+    solana_program::{
+        instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+        program_option::COption,
+    },
+    system_program::{
+        allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+    },
 };
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{Mint, TokenAccount, TokenInterface},
+    token_2022::spl_token_2022::{
+        extension::{
+            transfer_hook::{TransferHook as TransferHookExtension, TransferHookAccount},
+            BaseStateWithExtensions, PodStateWithExtensions,
+        },
+        pod::PodAccount,
+    },
+    token_interface::{get_mint_extension_data, Mint, TokenAccount, TokenInterface},
 };
 use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta,
     state::ExtraAccountMetaList,
 };
 use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+use spl_type_length_value::state::TlvStateBorrowed;
+
+pub mod errors;
+pub mod events;
+pub mod pda;
+pub mod state;
+
+use errors::TransferHookError;
+use events::{ReasonCode, TransferDecision, TransferEvaluated};
+use pda::{EXTRA_ACCOUNT_METAS_SEED, EXTRA_META_AUTHORITY_SEED};
+use state::ExtraMetaAuthority;
 
 declare_id!("H5UmP9c2qmeTMwLzZp7v7HbrR7W73Rw6bgo5ou8yDsDQ");
 
+// Flags returned by verify_setup, one per passing check
+#[constant]
+pub const SETUP_HOOK_PROGRAM: u8 = 1 << 0;
+#[constant]
+pub const SETUP_EXTRA_ACCOUNT_META_LIST: u8 = 1 << 1;
+#[constant]
+pub const SETUP_EXTRA_META_AUTHORITY: u8 = 1 << 2;
+
 #[program]
 pub mod transfer_hook {
     use super::*;
@@ -20,56 +58,274 @@ pub mod transfer_hook {
     pub fn initialize_extra_account_meta_list(
         ctx: Context<InitializeExtraAccountMetaList>,
     ) -> Result<()> {
+        create_extra_account_meta_list(
+            &ctx.accounts.payer,
+            &ctx.accounts.extra_account_meta_list,
+            &ctx.accounts.system_program,
+            &ctx.accounts.mint.key(),
+            ctx.bumps.extra_account_meta_list,
+        )?;
 
-        // The `addExtraAccountsToInstruction` JS helper function resolving incorrectly
-        let account_metas = vec![
-            
-        ];
+        // setup authority becomes the authority allowed to update the list later on
+        ctx.accounts.extra_meta_authority.set_inner(ExtraMetaAuthority {
+            authority: ctx.accounts.authority.key(),
+            pending_authority: None,
+            bump: ctx.bumps.extra_meta_authority,
+        });
+
+        msg!("Extra Account Meta List Initt");
+        Ok(())
+    }
+
+    // lists created before the ExtraMetaAuthority account existed have no authority,
+    // let the setup authority claim it once so they can be updated again
+    pub fn claim_extra_meta_authority(ctx: Context<ClaimExtraMetaAuthority>) -> Result<()> {
+        ctx.accounts.extra_meta_authority.set_inner(ExtraMetaAuthority {
+            authority: ctx.accounts.authority.key(),
+            pending_authority: None,
+            bump: ctx.bumps.extra_meta_authority,
+        });
+
+        msg!("Extra Meta Authority Claimed");
+        Ok(())
+    }
+
+    pub fn ensure_extra_account_meta_list(
+        ctx: Context<EnsureExtraAccountMetaList>,
+    ) -> Result<()> {
+        // never overwrite an existing list, updating it is left to the authority
+        if ctx.accounts.extra_account_meta_list.owner == ctx.program_id {
+            if !InitializeExtraAccountMetaList::is_current_layout(
+                &ctx.accounts.extra_account_meta_list.try_borrow_data()?,
+            )? {
+                msg!("Extra Account Meta List Outdated, use update_extra_account_meta_list");
+            }
 
-        // calculate account size
-        let account_size = ExtraAccountMetaList::size_of(account_metas.len())? as u64;
-        // calculate minimum required lamports
-        let lamports = Rent::get()?.minimum_balance(account_size as usize);
+            msg!("Extra Account Meta List Already Initialized");
+            return Ok(());
+        }
+
+        // only creating the list needs the setup authority, so re-running this once the
+        // mint authority has been revoked still succeeds
+        require!(
+            is_setup_authority(&ctx.accounts.mint, &ctx.accounts.authority.key()),
+            TransferHookError::NotSetupAuthority
+        );
 
         let mint = ctx.accounts.mint.key();
+        create_extra_account_meta_list(
+            &ctx.accounts.payer,
+            &ctx.accounts.extra_account_meta_list,
+            &ctx.accounts.system_program,
+            &mint,
+            ctx.bumps.extra_account_meta_list,
+        )?;
+
+        // create ExtraMetaAuthority account alongside the new list only, so an
+        // existing list can't have its authority claimed through this instruction
         let signer_seeds: &[&[&[u8]]] = &[&[
-            b"extra-account-metas",
-            &mint.as_ref(),
-            &[ctx.bumps.extra_account_meta_list],
+            EXTRA_META_AUTHORITY_SEED,
+            mint.as_ref(),
+            &[ctx.bumps.extra_meta_authority],
         ]];
 
-        // create ExtraAccountMetaList account
-        create_account(
-            CpiContext::new(
-                ctx.accounts.system_program.to_account_info(),
-                CreateAccount {
-                    from: ctx.accounts.payer.to_account_info(),
-                    to: ctx.accounts.extra_account_meta_list.to_account_info(),
-                },
-            )
-            .with_signer(signer_seeds),
-            lamports,
-            account_size,
-            ctx.program_id,
+        create_pda_account(
+            &ctx.accounts.payer,
+            &ctx.accounts.extra_meta_authority,
+            &ctx.accounts.system_program,
+            signer_seeds,
+            8 + ExtraMetaAuthority::INIT_SPACE,
         )?;
 
-        // initialize ExtraAccountMetaList account with extra accounts
-        ExtraAccountMetaList::init::<ExecuteInstruction>(
-            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+        // setup authority becomes the authority allowed to update the list later on
+        ExtraMetaAuthority {
+            authority: ctx.accounts.authority.key(),
+            pending_authority: None,
+            bump: ctx.bumps.extra_meta_authority,
+        }
+        .try_serialize(&mut &mut ctx.accounts.extra_meta_authority.try_borrow_mut_data()?[..])?;
+
+        msg!("Extra Account Meta List Initt");
+        Ok(())
+    }
+
+    pub fn update_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+    ) -> Result<()> {
+        let account_metas = InitializeExtraAccountMetaList::extra_account_metas()?;
+
+        // calculate account size for the current set of extra accounts
+        let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+        let extra_account_meta_list = ctx.accounts.extra_account_meta_list.to_account_info();
+
+        // the previous layout is unpacked to be rewritten, so a list that no longer
+        // parses has to be reset instead
+        if !InitializeExtraAccountMetaList::is_valid_layout(
+            &extra_account_meta_list.try_borrow_data()?,
+        ) {
+            msg!("Extra Account Meta List Invalid, use reset_extra_account_meta_list");
+            return err!(TransferHookError::InvalidExtraAccountMetaList);
+        }
+
+        // grow the account and top up rent if the new list no longer fits
+        if account_size > extra_account_meta_list.data_len() {
+            ctx.accounts.resize_extra_account_meta_list(account_size)?;
+        }
+
+        // rewrite ExtraAccountMetaList account in place with extra accounts
+        ExtraAccountMetaList::update::<ExecuteInstruction>(
+            &mut extra_account_meta_list.try_borrow_mut_data()?,
             &account_metas,
         )?;
 
-        msg!("Extra Account Meta List Initt");
+        msg!("Extra Account Meta List Updated");
+        Ok(())
+    }
+
+    pub fn reset_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+    ) -> Result<()> {
+        let account_metas = InitializeExtraAccountMetaList::extra_account_metas()?;
+
+        // resize the account to exactly fit the current set of extra accounts
+        let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+        ctx.accounts.resize_extra_account_meta_list(account_size)?;
+
+        // wipe the previous layout, which may no longer unpack, and initialize
+        // ExtraAccountMetaList account with extra accounts
+        let extra_account_meta_list = ctx.accounts.extra_account_meta_list.to_account_info();
+        let mut data = extra_account_meta_list.try_borrow_mut_data()?;
+        data.fill(0);
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &account_metas)?;
+
+        msg!("Extra Account Meta List Reset");
+        Ok(())
+    }
+
+    // stand-in for a list written by an older version of the program, with `len`
+    // of the current extra accounts, or garbage that no longer parses
+    #[cfg(feature = "test-extra-metas")]
+    pub fn write_test_extra_account_meta_list(
+        ctx: Context<UpdateExtraAccountMetaList>,
+        len: u8,
+        corrupt: bool,
+    ) -> Result<()> {
+        let account_metas: Vec<_> = InitializeExtraAccountMetaList::extra_account_metas()?
+            .into_iter()
+            .cycle()
+            .take(len as usize)
+            .collect();
+
+        let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+        ctx.accounts.resize_extra_account_meta_list(account_size)?;
+
+        let extra_account_meta_list = ctx.accounts.extra_account_meta_list.to_account_info();
+        let mut data = extra_account_meta_list.try_borrow_mut_data()?;
+        if corrupt {
+            data.fill(u8::MAX);
+            return Ok(());
+        }
+        data.fill(0);
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &account_metas)?;
+
+        Ok(())
+    }
+
+    // stand-in for a list created before the ExtraMetaAuthority account existed
+    #[cfg(feature = "test-extra-metas")]
+    pub fn close_test_extra_meta_authority(
+        _ctx: Context<CloseTestExtraMetaAuthority>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    // propose a new authority, which only takes over once it accepts, so a mistyped
+    // key can't lock the list
+    pub fn set_extra_meta_authority(
+        ctx: Context<SetExtraMetaAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        require!(
+            new_authority != Pubkey::default(),
+            TransferHookError::InvalidNewAuthority
+        );
+        ctx.accounts.extra_meta_authority.pending_authority = Some(new_authority);
+
+        msg!("Extra Meta Authority Proposed");
+        Ok(())
+    }
+
+    pub fn accept_extra_meta_authority(ctx: Context<AcceptExtraMetaAuthority>) -> Result<()> {
+        let extra_meta_authority = &mut ctx.accounts.extra_meta_authority;
+        extra_meta_authority.authority = ctx.accounts.pending_authority.key();
+        extra_meta_authority.pending_authority = None;
+
+        msg!("Extra Meta Authority Accepted");
+        Ok(())
+    }
+
+    pub fn cancel_extra_meta_authority(ctx: Context<SetExtraMetaAuthority>) -> Result<()> {
+        ctx.accounts.extra_meta_authority.pending_authority = None;
+
+        msg!("Extra Meta Authority Proposal Cancelled");
         Ok(())
     }
 
     pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+        // evaluate the transfer, and make sure the hook is being invoked by the
+        // token2022 program during a transfer
+        let result = ctx
+            .accounts
+            .evaluate()
+            .and_then(|_| ctx.accounts.check_invoked_via_cpi())
+            .and_then(|_| ctx.accounts.check_is_transferring());
+
+        // record the decision before any rejection is returned
+        let reason_code = ReasonCode::from(&result);
+        emit!(TransferEvaluated {
+            source_owner: ctx.accounts.owner.key(),
+            destination_owner: ctx.accounts.destination_token.owner,
+            amount,
+            approved: reason_code == ReasonCode::Approved,
+            reason_code,
+        });
+        result?;
 
         msg!("Hello Transfer Hook!");
 
         Ok(())
     }
 
+    // preflight the transfer hook decision without a transfer in progress
+    pub fn simulate_transfer(
+        ctx: Context<TransferHook>,
+        _amount: u64,
+    ) -> Result<TransferDecision> {
+        let reason_code = ReasonCode::from(&ctx.accounts.evaluate());
+
+        Ok(TransferDecision {
+            approved: reason_code == ReasonCode::Approved,
+            reason_code,
+        })
+    }
+
+    // report which parts of a mint's transfer hook setup are in place
+    pub fn verify_setup(ctx: Context<VerifySetup>) -> Result<u8> {
+        let mut flags = 0;
+
+        if check_hook_program(&ctx.accounts.mint).is_ok() {
+            flags |= SETUP_HOOK_PROGRAM;
+        }
+        if ctx.accounts.has_extra_account_meta_list()? {
+            flags |= SETUP_EXTRA_ACCOUNT_META_LIST;
+        }
+        if ctx.accounts.has_extra_meta_authority()? {
+            flags |= SETUP_EXTRA_META_AUTHORITY;
+        }
+
+        Ok(flags)
+    }
+
     // fallback instruction handler as workaround to anchor instruction discriminator check
     pub fn fallback<'info>(
         program_id: &Pubkey,
@@ -87,7 +343,7 @@ pub mod transfer_hook {
                 // invoke custom transfer hook instruction on our program
                 __private::__global::transfer_hook(program_id, accounts, &amount_bytes)
             }
-            _ => return Err(ProgramError::InvalidInstructionData.into()),
+            _ => Err(ProgramError::InvalidInstructionData.into()),
         }
     }
 }
@@ -96,20 +352,238 @@ pub mod transfer_hook {
 pub struct InitializeExtraAccountMetaList<'info> {
     #[account(mut)]
     payer: Signer<'info>,
+    pub authority: Signer<'info>,
 
     /// CHECK: ExtraAccountMetaList Account, must use these seeds
     #[account(
         mut,
-        seeds = [b"extra-account-metas", mint.key().as_ref()], 
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], 
         bump
     )]
     pub extra_account_meta_list: AccountInfo<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ExtraMetaAuthority::INIT_SPACE,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    #[account(
+        constraint = is_setup_authority(&mint, &authority.key())
+            @ TransferHookError::NotSetupAuthority
+    )]
     pub mint: InterfaceAccount<'info, Mint>,
     pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
+impl InitializeExtraAccountMetaList<'_> {
+    // Extra accounts required by the transfer hook instruction
+    #[cfg(not(feature = "test-extra-metas"))]
+    pub fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+        // The `addExtraAccountsToInstruction` JS helper function resolving incorrectly
+        Ok(vec![])
+    }
+
+    // Test builds require a few extra accounts, so that changes to the list can be exercised
+    #[cfg(feature = "test-extra-metas")]
+    pub fn extra_account_metas() -> Result<Vec<ExtraAccountMeta>> {
+        use anchor_lang::solana_program::sysvar;
+        use spl_tlv_account_resolution::seeds::Seed;
+
+        Ok(vec![
+            ExtraAccountMeta::new_with_pubkey(&anchor_lang::system_program::ID, false, false)?,
+            ExtraAccountMeta::new_with_pubkey(&sysvar::instructions::ID, false, false)?,
+            // mint's ExtraMetaAuthority account, index 1 is the mint
+            ExtraAccountMeta::new_with_seeds(
+                &[
+                    Seed::Literal {
+                        bytes: EXTRA_META_AUTHORITY_SEED.to_vec(),
+                    },
+                    Seed::AccountKey { index: 1 },
+                ],
+                false,
+                false,
+            )?,
+        ])
+    }
+
+    // Whether the data holds an ExtraAccountMetaList for the execute instruction
+    pub fn is_valid_layout(data: &[u8]) -> bool {
+        TlvStateBorrowed::unpack(data).is_ok_and(|state| {
+            ExtraAccountMetaList::unpack_with_tlv_state::<ExecuteInstruction>(&state).is_ok()
+        })
+    }
+
+    // Whether the data holds an ExtraAccountMetaList with the current extra accounts
+    pub fn is_current_layout(data: &[u8]) -> Result<bool> {
+        let Ok(state) = TlvStateBorrowed::unpack(data) else {
+            return Ok(false);
+        };
+        let Ok(extra_meta_list) =
+            ExtraAccountMetaList::unpack_with_tlv_state::<ExecuteInstruction>(&state)
+        else {
+            return Ok(false);
+        };
+
+        Ok(extra_meta_list.data() == Self::extra_account_metas()?.as_slice())
+    }
+}
+
+#[derive(Accounts)]
+pub struct EnsureExtraAccountMetaList<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    pub authority: Signer<'info>,
+
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds, may already be initialized
+    #[account(
+        mut,
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    /// CHECK: ExtraMetaAuthority Account, must use these seeds, only created with a new list
+    #[account(
+        mut,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_meta_authority: UncheckedAccount<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimExtraMetaAuthority<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    pub authority: Signer<'info>,
+
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds and already be initialized
+    #[account(
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()],
+        bump,
+        owner = crate::ID @ TransferHookError::InvalidExtraAccountMetaList
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ExtraMetaAuthority::INIT_SPACE,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    #[account(
+        constraint = is_setup_authority(&mint, &authority.key())
+            @ TransferHookError::NotSetupAuthority
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateExtraAccountMetaList<'info> {
+    #[account(mut)]
+    payer: Signer<'info>,
+    pub authority: Signer<'info>,
+
+    /// CHECK: ExtraAccountMetaList Account, must use these seeds
+    #[account(
+        mut,
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], 
+        bump
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+    #[account(
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump = extra_meta_authority.bump,
+        has_one = authority @ TransferHookError::Unauthorized
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+impl UpdateExtraAccountMetaList<'_> {
+    // Resize the ExtraAccountMetaList account, keeping it rent exempt at the new size
+    fn resize_extra_account_meta_list(&self, account_size: usize) -> Result<()> {
+        let extra_account_meta_list = self.extra_account_meta_list.to_account_info();
+        let lamports = Rent::get()?.minimum_balance(account_size);
+        let current_lamports = extra_account_meta_list.lamports();
+
+        if lamports > current_lamports {
+            // payer funds the rent for the extra space
+            transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    Transfer {
+                        from: self.payer.to_account_info(),
+                        to: extra_account_meta_list.clone(),
+                    },
+                ),
+                lamports - current_lamports,
+            )?;
+        } else if current_lamports > lamports {
+            // refund rent that is no longer needed to the payer
+            extra_account_meta_list.sub_lamports(current_lamports - lamports)?;
+            self.payer.add_lamports(current_lamports - lamports)?;
+        }
+
+        extra_account_meta_list.resize(account_size)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetExtraMetaAuthority<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump = extra_meta_authority.bump,
+        has_one = authority @ TransferHookError::Unauthorized
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptExtraMetaAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump = extra_meta_authority.bump,
+        constraint = extra_meta_authority.pending_authority == Some(pending_authority.key())
+            @ TransferHookError::NotPendingAuthority
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
+#[cfg(feature = "test-extra-metas")]
+#[derive(Accounts)]
+pub struct CloseTestExtraMetaAuthority<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump = extra_meta_authority.bump,
+        has_one = authority @ TransferHookError::Unauthorized
+    )]
+    pub extra_meta_authority: Account<'info, ExtraMetaAuthority>,
+    pub mint: InterfaceAccount<'info, Mint>,
+}
+
 // Order of accounts matters for this struct.
 // The first 4 accounts are the accounts required for token transfer (source, mint, destination, owner)
 // Remaining accounts are the extra accounts required from the ExtraAccountMetaList account
@@ -130,8 +604,312 @@ pub struct TransferHook<'info> {
     pub owner: UncheckedAccount<'info>,
     /// CHECK: ExtraAccountMetaList Account,
     #[account(
-        seeds = [b"extra-account-metas", mint.key().as_ref()], 
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()], 
+        bump
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+}
+
+impl TransferHook<'_> {
+    // make sure the mint being transferred is actually hooked to this program,
+    // that neither token account is frozen, and that the mint's ExtraAccountMetaList
+    // account is valid
+    fn evaluate(&self) -> Result<()> {
+        self.check_hook_program()?;
+        self.check_not_frozen()?;
+        self.check_extra_account_meta_list()
+    }
+
+    fn check_hook_program(&self) -> Result<()> {
+        check_hook_program(&self.mint)
+    }
+
+    fn check_not_frozen(&self) -> Result<()> {
+        // token2022 already refuses to move tokens out of or into a frozen account,
+        // this surfaces a descriptive error if the hook is ever reached regardless
+        require!(
+            !self.source_token.is_frozen() && !self.destination_token.is_frozen(),
+            TransferHookError::AccountFrozen
+        );
+
+        Ok(())
+    }
+
+    fn check_extra_account_meta_list(&self) -> Result<()> {
+        // seeds only pin the address, the account must also hold a list written by this program
+        require!(
+            self.extra_account_meta_list.owner == &crate::ID
+                && InitializeExtraAccountMetaList::is_valid_layout(
+                    &self.extra_account_meta_list.try_borrow_data()?
+                ),
+            TransferHookError::InvalidExtraAccountMetaList
+        );
+
+        Ok(())
+    }
+
+    fn check_invoked_via_cpi(&self) -> Result<()> {
+        // token2022 CPIs this program on token transfer, so a transaction level
+        // invocation can't be a transfer. Deeper nesting is allowed, since programs
+        // that CPI token2022 themselves push the hook further down the stack
+        require!(
+            get_stack_height() > TRANSACTION_LEVEL_STACK_HEIGHT,
+            TransferHookError::NotInvokedViaCpi
+        );
+
+        Ok(())
+    }
+
+    fn check_is_transferring(&self) -> Result<()> {
+        // token2022 only sets the source account's transferring flag for the duration
+        // of the transfer, so another program can't invoke the hook and pass this check
+        require!(
+            is_transferring(&self.source_token.to_account_info().try_borrow_data()?)?,
+            TransferHookError::NotTransferring
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct VerifySetup<'info> {
+    pub mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: ExtraAccountMetaList Account, may not be initialized yet
+    #[account(
+        seeds = [EXTRA_ACCOUNT_METAS_SEED, mint.key().as_ref()],
         bump
     )]
     pub extra_account_meta_list: UncheckedAccount<'info>,
+    /// CHECK: ExtraMetaAuthority Account, may not be initialized yet
+    #[account(
+        seeds = [EXTRA_META_AUTHORITY_SEED, mint.key().as_ref()],
+        bump
+    )]
+    pub extra_meta_authority: UncheckedAccount<'info>,
+}
+
+impl VerifySetup<'_> {
+    fn has_extra_account_meta_list(&self) -> Result<bool> {
+        if self.extra_account_meta_list.owner != &crate::ID {
+            return Ok(false);
+        }
+
+        InitializeExtraAccountMetaList::is_current_layout(
+            &self.extra_account_meta_list.try_borrow_data()?,
+        )
+    }
+
+    fn has_extra_meta_authority(&self) -> Result<bool> {
+        if self.extra_meta_authority.owner != &crate::ID {
+            return Ok(false);
+        }
+
+        let data = self.extra_meta_authority.try_borrow_data()?;
+        Ok(ExtraMetaAuthority::try_deserialize(&mut &data[..]).is_ok())
+    }
+}
+
+// Make sure the mint's TransferHook extension points to this program
+fn check_hook_program(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let transfer_hook = get_mint_extension_data::<TransferHookExtension>(&mint.to_account_info())
+        .map_err(|_| TransferHookError::WrongHookProgram)?;
+
+    require!(
+        Option::<Pubkey>::from(transfer_hook.program_id) == Some(crate::ID),
+        TransferHookError::WrongHookProgram
+    );
+
+    Ok(())
+}
+
+// Read the transferring flag of a token account's TransferHookAccount extension
+fn is_transferring(data: &[u8]) -> Result<bool> {
+    let account = PodStateWithExtensions::<PodAccount>::unpack(data)
+        .map_err(|_| ErrorCode::AccountDidNotDeserialize)?;
+    let extension = account
+        .get_extension::<TransferHookAccount>()
+        .map_err(|_| TransferHookError::MissingTransferHookExtension)?;
+
+    Ok(bool::from(extension.transferring))
+}
+
+// Whether the key may set up the mint's ExtraAccountMetaList account, as either its mint
+// authority or the authority of its TransferHook extension. The latter covers mints whose
+// mint authority is a multisig or has been revoked
+fn is_setup_authority(mint: &InterfaceAccount<Mint>, authority: &Pubkey) -> bool {
+    mint.mint_authority == COption::Some(*authority)
+        || get_mint_extension_data::<TransferHookExtension>(&mint.to_account_info())
+            .is_ok_and(|transfer_hook| {
+                Option::<Pubkey>::from(transfer_hook.authority) == Some(*authority)
+            })
+}
+
+// Create and initialize a mint's ExtraAccountMetaList account with the current extra accounts
+fn create_extra_account_meta_list<'info>(
+    payer: &Signer<'info>,
+    extra_account_meta_list: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    mint: &Pubkey,
+    bump: u8,
+) -> Result<()> {
+    let account_metas = InitializeExtraAccountMetaList::extra_account_metas()?;
+
+    // calculate account size
+    let account_size = ExtraAccountMetaList::size_of(account_metas.len())?;
+
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        EXTRA_ACCOUNT_METAS_SEED,
+        mint.as_ref(),
+        &[bump],
+    ]];
+
+    // create ExtraAccountMetaList account
+    create_pda_account(
+        payer,
+        extra_account_meta_list,
+        system_program,
+        signer_seeds,
+        account_size,
+    )?;
+
+    // initialize ExtraAccountMetaList account with extra accounts
+    ExtraAccountMetaList::init::<ExecuteInstruction>(
+        &mut extra_account_meta_list.try_borrow_mut_data()?,
+        &account_metas,
+    )?;
+
+    Ok(())
+}
+
+// Create a rent exempt account owned by this program at a PDA, even if someone
+// already sent lamports to its address
+fn create_pda_account<'info>(
+    payer: &Signer<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    signer_seeds: &[&[&[u8]]],
+    space: usize,
+) -> Result<()> {
+    // calculate minimum required lamports
+    let lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = account.lamports();
+
+    if current_lamports == 0 {
+        return create_account(
+            CpiContext::new(
+                system_program.to_account_info(),
+                CreateAccount {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            )
+            .with_signer(signer_seeds),
+            lamports,
+            space as u64,
+            &crate::ID,
+        );
+    }
+
+    // create_account refuses an address that already holds lamports, so top up the
+    // rent and allocate and assign the account instead, as anchor's init does
+    if lamports > current_lamports {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            lamports - current_lamports,
+        )?;
+    }
+    allocate(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Allocate {
+                account_to_allocate: account.clone(),
+            },
+        )
+        .with_signer(signer_seeds),
+        space as u64,
+    )?;
+    assign(
+        CpiContext::new(
+            system_program.to_account_info(),
+            Assign {
+                account_to_assign: account.clone(),
+            },
+        )
+        .with_signer(signer_seeds),
+        &crate::ID,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::program_pack::Pack;
+    use anchor_spl::token_2022::spl_token_2022::{
+        extension::{BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut},
+        state::{Account, AccountState},
+    };
+
+    fn token_account() -> Account {
+        Account {
+            mint: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            state: AccountState::Initialized,
+            ..Account::default()
+        }
+    }
+
+    // token account data with a TransferHookAccount extension, as token2022 creates
+    // it for accounts of a mint with a transfer hook
+    fn token_account_with_transfer_hook(transferring: bool) -> Vec<u8> {
+        let account_len = ExtensionType::try_calculate_account_len::<Account>(&[
+            ExtensionType::TransferHookAccount,
+        ])
+        .unwrap();
+        let mut data = vec![0; account_len];
+
+        let mut state = StateWithExtensionsMut::<Account>::unpack_uninitialized(&mut data).unwrap();
+        state.base = token_account();
+        state.pack_base();
+        state.init_account_type().unwrap();
+        state
+            .init_extension::<TransferHookAccount>(true)
+            .unwrap()
+            .transferring = transferring.into();
+
+        data
+    }
+
+    #[test]
+    fn is_transferring_reads_transferring_flag() {
+        assert_eq!(is_transferring(&token_account_with_transfer_hook(true)), Ok(true));
+        assert_eq!(is_transferring(&token_account_with_transfer_hook(false)), Ok(false));
+    }
+
+    #[test]
+    fn is_transferring_rejects_account_without_transfer_hook_extension() {
+        let mut data = vec![0; Account::LEN];
+        Account::pack(token_account(), &mut data).unwrap();
+
+        assert_eq!(
+            is_transferring(&data),
+            Err(TransferHookError::MissingTransferHookExtension.into())
+        );
+    }
+
+    #[test]
+    fn is_transferring_rejects_truncated_account() {
+        let data = token_account_with_transfer_hook(true);
+
+        assert_eq!(
+            is_transferring(&data[..Account::LEN - 1]),
+            Err(ErrorCode::AccountDidNotDeserialize.into())
+        );
+    }
 }
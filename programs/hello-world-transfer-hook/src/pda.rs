@@ -0,0 +1,50 @@
+use anchor_lang::prelude::*;
+
+pub const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+pub const EXTRA_META_AUTHORITY_SEED: &[u8] = b"extra-meta-authority";
+
+// ExtraAccountMetaList account address and bump for a mint
+pub fn extra_account_meta_list_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], &crate::ID)
+}
+
+// ExtraMetaAuthority account address and bump for a mint
+pub fn extra_meta_authority_pda(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[EXTRA_META_AUTHORITY_SEED, mint.as_ref()], &crate::ID)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spl_transfer_hook_interface::get_extra_account_metas_address;
+
+    #[test]
+    fn extra_account_meta_list_pda_matches_transfer_hook_interface() {
+        let mint = Pubkey::new_unique();
+        let (address, bump) = extra_account_meta_list_pda(&mint);
+
+        // token2022 resolves the list through the transfer hook interface derivation
+        assert_eq!(address, get_extra_account_metas_address(&mint, &crate::ID));
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref(), &[bump]],
+                &crate::ID
+            ),
+            Ok(address)
+        );
+    }
+
+    #[test]
+    fn extra_meta_authority_pda_derives_from_mint() {
+        let mint = Pubkey::new_unique();
+        let (address, bump) = extra_meta_authority_pda(&mint);
+
+        // clients derive the authority account from the literal seed
+        assert_eq!(
+            (address, bump),
+            Pubkey::find_program_address(&[b"extra-meta-authority", mint.as_ref()], &crate::ID)
+        );
+        assert_ne!(address, extra_account_meta_list_pda(&mint).0);
+        assert_ne!(address, extra_meta_authority_pda(&Pubkey::new_unique()).0);
+    }
+}
@@ -0,0 +1,11 @@
+use anchor_lang::prelude::*;
+
+// Authority allowed to update or reset a mint's ExtraAccountMetaList account
+#[account]
+#[derive(InitSpace)]
+pub struct ExtraMetaAuthority {
+    pub authority: Pubkey,
+    // proposed by the authority, takes over once it accepts
+    pub pending_authority: Option<Pubkey>,
+    pub bump: u8,
+}
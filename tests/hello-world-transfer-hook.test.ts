@@ -7,6 +7,7 @@ import {
   Transaction,
   sendAndConfirmTransaction,
   Keypair,
+  ComputeBudgetProgram,
 } from "@solana/web3.js";
 import {
   ExtensionType,
@@ -20,7 +21,13 @@ import {
   createTransferCheckedInstruction,
   getAssociatedTokenAddressSync,
   createTransferCheckedWithTransferHookInstruction,
+  createExecuteInstruction,
+  getExtraAccountMetas,
+  createFreezeAccountInstruction,
+  createSetAuthorityInstruction,
+  AuthorityType,
 } from "@solana/spl-token";
+import { expect } from "chai";
 
 describe("transfer-hook", () => {
   // Configure the client to use the local cluster.
@@ -31,6 +38,22 @@ describe("transfer-hook", () => {
   const wallet = provider.wallet as anchor.Wallet;
   const connection = provider.connection;
 
+  // ExtraAccountMetaList address of a mint
+  // Store extra accounts required by the custom transfer hook instruction
+  const extraAccountMetaListPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-account-metas"), mint.toBuffer()],
+      program.programId
+    )[0];
+
+  // ExtraMetaAuthority address of a mint
+  // Store the authority allowed to update the ExtraAccountMetaList account
+  const extraMetaAuthorityPda = (mint: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-meta-authority"), mint.toBuffer()],
+      program.programId
+    )[0];
+
   // Generate keypair to use as address for the transfer-hook enabled mint
   const mint = new Keypair();
   const decimals = 9;
@@ -54,12 +77,113 @@ describe("transfer-hook", () => {
     ASSOCIATED_TOKEN_PROGRAM_ID
   );
 
-  // ExtraAccountMetaList address
-  // Store extra accounts required by the custom transfer hook instruction
-  const [extraAccountMetaListPDA] = PublicKey.findProgramAddressSync(
-    [Buffer.from("extra-account-metas"), mint.publicKey.toBuffer()],
-    program.programId
-  );
+  // ExtraAccountMetaList and ExtraMetaAuthority addresses of the transfer-hook enabled mint
+  const extraAccountMetaListPDA = extraAccountMetaListPda(mint.publicKey);
+  const extraMetaAuthorityPDA = extraMetaAuthorityPda(mint.publicKey);
+
+  // Extra accounts required by the program, which `yarn test` builds with test-extra-metas
+  const extraAccountMetaCount = 3;
+
+  // Send a transaction that is expected to fail and check the program logs for the error
+  const expectTransactionError = async (
+    transaction: Transaction,
+    error: string,
+    signers: Keypair[] = [wallet.payer]
+  ) => {
+    const err = await sendAndConfirmTransaction(connection, transaction, signers).then(
+      () => null,
+      (err) => err
+    );
+    expect(err, `transaction should fail with ${error}`).to.not.be.null;
+    expect(err.logs.join("\n")).to.include(error);
+    return err;
+  };
+
+  // Decode the TransferEvaluated events emitted by the transfer hook from the program logs
+  const eventParser = new anchor.EventParser(program.programId, new anchor.BorshCoder(program.idl));
+  const parseTransferEvaluated = (logs: string[]) =>
+    Array.from(eventParser.parseLogs(logs))
+      .filter((event) => event.name === "transferEvaluated")
+      .map((event) => event.data);
+
+  // Create a transfer-hook enabled mint along with source and destination token accounts,
+  // and build a transfer hook interface execute instruction invoking this program directly
+  const setupHookedMint = async (hookProgramId: PublicKey, freezeAuthority: PublicKey | null) => {
+    const hookedMint = new Keypair();
+
+    const extensions = [ExtensionType.TransferHook];
+    const mintLen = getMintLen(extensions);
+    const lamports = await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+    const source = getAssociatedTokenAddressSync(
+      hookedMint.publicKey,
+      wallet.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    const destination = getAssociatedTokenAddressSync(
+      hookedMint.publicKey,
+      recipient.publicKey,
+      false,
+      TOKEN_2022_PROGRAM_ID,
+      ASSOCIATED_TOKEN_PROGRAM_ID
+    );
+    const hookedExtraAccountMetaListPDA = extraAccountMetaListPda(hookedMint.publicKey);
+
+    const transaction = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: wallet.publicKey,
+        newAccountPubkey: hookedMint.publicKey,
+        space: mintLen,
+        lamports: lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferHookInstruction(
+        hookedMint.publicKey,
+        wallet.publicKey,
+        hookProgramId,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        hookedMint.publicKey,
+        decimals,
+        wallet.publicKey,
+        freezeAuthority,
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createAssociatedTokenAccountInstruction(
+        wallet.publicKey,
+        source,
+        wallet.publicKey,
+        hookedMint.publicKey,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      ),
+      createAssociatedTokenAccountInstruction(
+        wallet.publicKey,
+        destination,
+        recipient.publicKey,
+        hookedMint.publicKey,
+        TOKEN_2022_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID
+      )
+    );
+
+    await sendAndConfirmTransaction(connection, transaction, [wallet.payer, hookedMint]);
+
+    const executeInstruction = createExecuteInstruction(
+      program.programId,
+      source,
+      hookedMint.publicKey,
+      destination,
+      wallet.publicKey,
+      hookedExtraAccountMetaListPDA,
+      BigInt(1 * 10 ** decimals)
+    );
+
+    return { mint: hookedMint.publicKey, source, destination, executeInstruction };
+  };
 
   it("Create Mint Account with Transfer Hook Extension", async () => {
     const extensions = [ExtensionType.TransferHook];
@@ -145,7 +269,9 @@ describe("transfer-hook", () => {
       .initializeExtraAccountMetaList()
       .accounts({
         payer: wallet.publicKey,
+        authority: wallet.publicKey,
         extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
         mint: mint.publicKey,
         tokenProgram: TOKEN_2022_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
@@ -162,6 +288,359 @@ describe("transfer-hook", () => {
       { skipPreflight: true, commitment: "confirmed" }
     );
     console.log("Transaction Signature:", txSig);
+
+    // The signing mint authority becomes the ExtraAccountMetaList authority
+    const extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      extraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(extraMetaAuthority.pendingAuthority).to.be.null;
+  });
+
+  // Overwrite the ExtraAccountMetaList account with a list of `len` extra accounts, as an older
+  // version of the program would have written it, or with data that no longer parses
+  const writeTestExtraAccountMetaList = (
+    len: number,
+    corrupt: boolean,
+    hookedMint: PublicKey = mint.publicKey
+  ) =>
+    program.methods
+      .writeTestExtraAccountMetaList(len, corrupt)
+      .accounts({
+        payer: wallet.publicKey,
+        authority: wallet.publicKey,
+        extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+        extraMetaAuthority: extraMetaAuthorityPda(hookedMint),
+        mint: hookedMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+  // Rewrite the ExtraAccountMetaList account in place with the current extra accounts
+  it("Update ExtraAccountMetaList Account", async () => {
+    // Start from a list with a single extra account, so the update has to grow it
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await writeTestExtraAccountMetaList(1, false)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const previous = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    expect(getExtraAccountMetas(previous)).to.have.length(1);
+
+    const updateExtraAccountMetaListInstruction = await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority: wallet.publicKey,
+        extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+    const transaction = new Transaction().add(updateExtraAccountMetaListInstruction);
+
+    const txSig = await sendAndConfirmTransaction(
+      provider.connection,
+      transaction,
+      [wallet.payer],
+      { skipPreflight: true, commitment: "confirmed" }
+    );
+    console.log("Transaction Signature:", txSig);
+
+    // The account grew to hold every current extra account and is still rent exempt.
+    // The transfer below resolves the extra accounts from the updated list
+    const accountInfo = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    const rentExemptLamports = await connection.getMinimumBalanceForRentExemption(
+      accountInfo.data.length
+    );
+    expect(accountInfo.data.length).to.be.greaterThan(previous.data.length);
+    expect(getExtraAccountMetas(accountInfo)).to.have.length(extraAccountMetaCount);
+    expect(accountInfo.lamports).to.equal(rentExemptLamports);
+  });
+
+  it("Reject ExtraAccountMetaList Update of a list that no longer parses", async () => {
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await writeTestExtraAccountMetaList(1, true)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    const updateExtraAccountMetaListInstruction = await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority: wallet.publicKey,
+        extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+    const err = await expectTransactionError(
+      new Transaction().add(updateExtraAccountMetaListInstruction),
+      "InvalidExtraAccountMetaList"
+    );
+    expect(err.logs.join("\n")).to.include("use reset_extra_account_meta_list");
+  });
+
+  // Wipe and reinitialize the ExtraAccountMetaList account with the current extra accounts
+  const resetExtraAccountMetaList = (authority: PublicKey) =>
+    program.methods
+      .resetExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority,
+        extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+  // The account holds exactly the current layout and is still rent exempt
+  const expectCurrentExtraAccountMetaList = async () => {
+    const accountInfo = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    const rentExemptLamports = await connection.getMinimumBalanceForRentExemption(
+      accountInfo.data.length
+    );
+    expect(accountInfo.owner.toBase58()).to.equal(program.programId.toBase58());
+    expect(getExtraAccountMetas(accountInfo)).to.have.length(extraAccountMetaCount);
+    expect(accountInfo.lamports).to.equal(rentExemptLamports);
+    return accountInfo;
+  };
+
+  // The list left behind by the previous test is shorter than the current layout
+  // and no longer parses, so the reset has to grow it
+  it("Reset ExtraAccountMetaList Account", async () => {
+    const previous = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+
+    const txSig = await sendAndConfirmTransaction(
+      provider.connection,
+      new Transaction().add(await resetExtraAccountMetaList(wallet.publicKey)),
+      [wallet.payer],
+      { skipPreflight: true, commitment: "confirmed" }
+    );
+    console.log("Transaction Signature:", txSig);
+
+    const accountInfo = await expectCurrentExtraAccountMetaList();
+    expect(accountInfo.data.length).to.be.greaterThan(previous.data.length);
+  });
+
+  it("Reset ExtraAccountMetaList Account larger than the current layout", async () => {
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await writeTestExtraAccountMetaList(2 * extraAccountMetaCount, false)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const previous = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    const payerBefore = await connection.getBalance(wallet.publicKey, "confirmed");
+
+    const txSig = await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await resetExtraAccountMetaList(wallet.publicKey)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    // The account shrank, and the rent that is no longer needed went back to the payer
+    const accountInfo = await expectCurrentExtraAccountMetaList();
+    expect(accountInfo.data.length).to.be.lessThan(previous.data.length);
+
+    const tx = await connection.getTransaction(txSig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const payerAfter = await connection.getBalance(wallet.publicKey, "confirmed");
+    expect(payerAfter - payerBefore).to.equal(
+      previous.lamports - accountInfo.lamports - tx.meta.fee
+    );
+  });
+
+  it("Reject ExtraAccountMetaList Reset from non-authority", async () => {
+    const impostor = Keypair.generate();
+
+    await expectTransactionError(
+      new Transaction().add(await resetExtraAccountMetaList(impostor.publicKey)),
+      "Unauthorized",
+      [wallet.payer, impostor]
+    );
+  });
+
+  it("Reject ExtraAccountMetaList Update from non-authority", async () => {
+    const impostor = Keypair.generate();
+
+    const updateExtraAccountMetaListInstruction = await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority: impostor.publicKey,
+        extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+    await expectTransactionError(
+      new Transaction().add(updateExtraAccountMetaListInstruction),
+      "Unauthorized",
+      [wallet.payer, impostor]
+    );
+  });
+
+  const setExtraMetaAuthority = (authority: PublicKey, newAuthority: PublicKey) =>
+    program.methods
+      .setExtraMetaAuthority(newAuthority)
+      .accounts({
+        authority,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+      })
+      .instruction();
+
+  const acceptExtraMetaAuthority = (pendingAuthority: PublicKey) =>
+    program.methods
+      .acceptExtraMetaAuthority()
+      .accounts({
+        pendingAuthority,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+      })
+      .instruction();
+
+  // Hand the ExtraAccountMetaList authority over, then update the list with the new authority
+  it("Set ExtraAccountMetaList Authority", async () => {
+    const newAuthority = Keypair.generate();
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        await setExtraMetaAuthority(wallet.publicKey, newAuthority.publicKey)
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    // The new authority is only proposed until it accepts
+    let extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      extraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(extraMetaAuthority.pendingAuthority.toBase58()).to.equal(
+      newAuthority.publicKey.toBase58()
+    );
+
+    const impostor = Keypair.generate();
+    await expectTransactionError(
+      new Transaction().add(await acceptExtraMetaAuthority(impostor.publicKey)),
+      "NotPendingAuthority",
+      [wallet.payer, impostor]
+    );
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await acceptExtraMetaAuthority(newAuthority.publicKey)),
+      [wallet.payer, newAuthority],
+      { commitment: "confirmed" }
+    );
+
+    extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      extraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(newAuthority.publicKey.toBase58());
+    expect(extraMetaAuthority.pendingAuthority).to.be.null;
+
+    const updateExtraAccountMetaListInstruction = await program.methods
+      .updateExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority: newAuthority.publicKey,
+        extraAccountMetaList: extraAccountMetaListPDA,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(updateExtraAccountMetaListInstruction),
+      [wallet.payer, newAuthority]
+    );
+
+    // Hand the authority back for the remaining tests
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        await setExtraMetaAuthority(newAuthority.publicKey, wallet.publicKey),
+        await acceptExtraMetaAuthority(wallet.publicKey)
+      ),
+      [wallet.payer, newAuthority],
+      { commitment: "confirmed" }
+    );
+  });
+
+  it("Reject ExtraAccountMetaList Authority handover from non-authority", async () => {
+    const impostor = Keypair.generate();
+
+    await expectTransactionError(
+      new Transaction().add(await setExtraMetaAuthority(impostor.publicKey, impostor.publicKey)),
+      "Unauthorized",
+      [wallet.payer, impostor]
+    );
+  });
+
+  it("Reject ExtraAccountMetaList Authority handover to the default public key", async () => {
+    await expectTransactionError(
+      new Transaction().add(await setExtraMetaAuthority(wallet.publicKey, PublicKey.default)),
+      "InvalidNewAuthority"
+    );
+  });
+
+  it("Cancel ExtraAccountMetaList Authority handover", async () => {
+    const newAuthority = Keypair.generate();
+
+    const cancelExtraMetaAuthorityInstruction = await program.methods
+      .cancelExtraMetaAuthority()
+      .accounts({
+        authority: wallet.publicKey,
+        extraMetaAuthority: extraMetaAuthorityPDA,
+        mint: mint.publicKey,
+      })
+      .instruction();
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        await setExtraMetaAuthority(wallet.publicKey, newAuthority.publicKey),
+        cancelExtraMetaAuthorityInstruction
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    const extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      extraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(extraMetaAuthority.pendingAuthority).to.be.null;
+
+    // The cancelled proposal can no longer be accepted
+    await expectTransactionError(
+      new Transaction().add(await acceptExtraMetaAuthority(newAuthority.publicKey)),
+      "NotPendingAuthority",
+      [wallet.payer, newAuthority]
+    );
   });
 
   it("Transfer Hook with Extra Account Meta", async () => {
@@ -189,8 +668,428 @@ describe("transfer-hook", () => {
       connection,
       transaction,
       [wallet.payer],
-      { skipPreflight: true }
+      { skipPreflight: true, commitment: "confirmed" }
     );
     console.log("Transfer Signature:", txSig);
+
+    // The hook records its decision for every transfer it evaluates
+    const tx = await connection.getTransaction(txSig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const [event] = parseTransferEvaluated(tx.meta.logMessages);
+    expect(event.sourceOwner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(event.destinationOwner.toBase58()).to.equal(recipient.publicKey.toBase58());
+    expect(event.amount.toString()).to.equal(bigIntAmount.toString());
+    expect(event.approved).to.be.true;
+    expect(event.reasonCode).to.have.property("approved");
+  });
+
+  // The fallback handler routes the transfer hook interface execute instruction to the
+  // transfer_hook instruction, which rejects it when not invoked by the token2022 program
+  it("Reject Direct Transfer Hook Interface Execute Instruction", async () => {
+    const executeInstruction = createExecuteInstruction(
+      program.programId,
+      sourceTokenAccount,
+      mint.publicKey,
+      destinationTokenAccount,
+      wallet.publicKey,
+      extraAccountMetaListPDA,
+      BigInt(1 * 10 ** decimals)
+    );
+
+    const err = await expectTransactionError(
+      new Transaction().add(executeInstruction),
+      "NotInvokedViaCpi"
+    );
+
+    // The decision is recorded before the rejection is returned
+    const [event] = parseTransferEvaluated(err.logs);
+    expect(event.sourceOwner.toBase58()).to.equal(wallet.publicKey.toBase58());
+    expect(event.approved).to.be.false;
+    expect(event.reasonCode).to.have.property("notInvokedViaCpi");
+  });
+
+  it("Reject Mint whose Transfer Hook points to another program", async () => {
+    // Mint configured with a transfer hook program other than this one
+    const { executeInstruction } = await setupHookedMint(Keypair.generate().publicKey, null);
+
+    await expectTransactionError(new Transaction().add(executeInstruction), "WrongHookProgram");
+  });
+
+  it("Reject Transfer into a Frozen Token Account", async () => {
+    const { mint: hookedMint, destination, executeInstruction } = await setupHookedMint(
+      program.programId,
+      wallet.publicKey
+    );
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        createFreezeAccountInstruction(
+          destination,
+          hookedMint,
+          wallet.publicKey,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [wallet.payer]
+    );
+
+    await expectTransactionError(new Transaction().add(executeInstruction), "AccountFrozen");
+  });
+
+  it("Reject Missing ExtraAccountMetaList Account", async () => {
+    // Mint hooked to this program whose ExtraAccountMetaList PDA was never initialized
+    const { executeInstruction } = await setupHookedMint(program.programId, null);
+
+    await expectTransactionError(
+      new Transaction().add(executeInstruction),
+      "InvalidExtraAccountMetaList"
+    );
+  });
+
+  const simulateTransfer = (hookedMint: PublicKey, source: PublicKey, destination: PublicKey) =>
+    program.methods
+      .simulateTransfer(new anchor.BN(1 * 10 ** decimals))
+      .accounts({
+        sourceToken: source,
+        mint: hookedMint,
+        destinationToken: destination,
+        owner: wallet.publicKey,
+        extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+      })
+      .view();
+
+  // Preflight the hook decision and compare it against the outcome of the real hook
+  it("Simulate Transfer Hook Decision", async () => {
+    // Transfers of the transfer-hook enabled mint are approved, as in the real transfer
+    const approved = await simulateTransfer(
+      mint.publicKey,
+      sourceTokenAccount,
+      destinationTokenAccount
+    );
+    expect(approved.approved).to.be.true;
+    expect(approved.reasonCode).to.have.property("approved");
+
+    // Mint hooked to another program
+    const wrongHook = await setupHookedMint(Keypair.generate().publicKey, null);
+    const wrongHookDecision = await simulateTransfer(
+      wrongHook.mint,
+      wrongHook.source,
+      wrongHook.destination
+    );
+    expect(wrongHookDecision.approved).to.be.false;
+    expect(wrongHookDecision.reasonCode).to.have.property("wrongHookProgram");
+    await expectTransactionError(
+      new Transaction().add(wrongHook.executeInstruction),
+      "WrongHookProgram"
+    );
+
+    // Frozen destination token account
+    const frozen = await setupHookedMint(program.programId, wallet.publicKey);
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        createFreezeAccountInstruction(
+          frozen.destination,
+          frozen.mint,
+          wallet.publicKey,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const frozenDecision = await simulateTransfer(frozen.mint, frozen.source, frozen.destination);
+    expect(frozenDecision.approved).to.be.false;
+    expect(frozenDecision.reasonCode).to.have.property("accountFrozen");
+    await expectTransactionError(new Transaction().add(frozen.executeInstruction), "AccountFrozen");
+  });
+
+  const initializeExtraAccountMetaList = (hookedMint: PublicKey, authority: PublicKey) =>
+    program.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        payer: wallet.publicKey,
+        authority,
+        extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+        extraMetaAuthority: extraMetaAuthorityPda(hookedMint),
+        mint: hookedMint,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+  const claimExtraMetaAuthority = (hookedMint: PublicKey, authority: PublicKey) =>
+    program.methods
+      .claimExtraMetaAuthority()
+      .accounts({
+        payer: wallet.publicKey,
+        authority,
+        extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+        extraMetaAuthority: extraMetaAuthorityPda(hookedMint),
+        mint: hookedMint,
+        systemProgram: SystemProgram.programId,
+      })
+      .instruction();
+
+  it("Reject ExtraAccountMetaList Account that no longer parses", async () => {
+    // Mint hooked to this program whose ExtraAccountMetaList PDA is owned by this program but
+    // holds data that no longer parses
+    const hooked = await setupHookedMint(program.programId, null);
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        await initializeExtraAccountMetaList(hooked.mint, wallet.publicKey),
+        await writeTestExtraAccountMetaList(1, true, hooked.mint)
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    const decision = await simulateTransfer(hooked.mint, hooked.source, hooked.destination);
+    expect(decision.approved).to.be.false;
+    expect(decision.reasonCode).to.have.property("invalidExtraAccountMetaList");
+    await expectTransactionError(
+      new Transaction().add(hooked.executeInstruction),
+      "InvalidExtraAccountMetaList"
+    );
+  });
+
+  it("Verify Transfer Hook Setup", async () => {
+    const verifySetup = (hookedMint: PublicKey) =>
+      program.methods
+        .verifySetup()
+        .accounts({
+          mint: hookedMint,
+          extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+          extraMetaAuthority: extraMetaAuthorityPda(hookedMint),
+        })
+        .view();
+
+    const hookProgram = 1 << 0;
+    const extraAccountMetaList = 1 << 1;
+    const extraMetaAuthority = 1 << 2;
+
+    // Fully configured transfer-hook enabled mint
+    expect(await verifySetup(mint.publicKey)).to.equal(
+      hookProgram | extraAccountMetaList | extraMetaAuthority
+    );
+
+    // Mint hooked to this program that never had its ExtraAccountMetaList account created
+    const unconfigured = await setupHookedMint(program.programId, null);
+    expect(await verifySetup(unconfigured.mint)).to.equal(hookProgram);
+  });
+
+  // Deployment scripts can ensure the ExtraAccountMetaList account exists on every run
+  it("Ensure ExtraAccountMetaList Account", async () => {
+    const ensureExtraAccountMetaList = (hookedMint: PublicKey) =>
+      program.methods
+        .ensureExtraAccountMetaList()
+        .accounts({
+          payer: wallet.publicKey,
+          authority: wallet.publicKey,
+          extraAccountMetaList: extraAccountMetaListPda(hookedMint),
+          extraMetaAuthority: extraMetaAuthorityPda(hookedMint),
+          mint: hookedMint,
+          systemProgram: SystemProgram.programId,
+        })
+        .instruction();
+
+    // First run on a fresh mint creates the list and its authority
+    const { mint: hookedMint } = await setupHookedMint(program.programId, null);
+    const hookedExtraAccountMetaListPDA = extraAccountMetaListPda(hookedMint);
+    const hookedExtraMetaAuthorityPDA = extraMetaAuthorityPda(hookedMint);
+
+    // Lamports sent to either address beforehand must not block the creation
+    const prefundLamports = await connection.getMinimumBalanceForRentExemption(0);
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        SystemProgram.transfer({
+          fromPubkey: wallet.publicKey,
+          toPubkey: hookedExtraAccountMetaListPDA,
+          lamports: prefundLamports,
+        }),
+        SystemProgram.transfer({
+          fromPubkey: wallet.publicKey,
+          toPubkey: hookedExtraMetaAuthorityPDA,
+          lamports: prefundLamports,
+        })
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await ensureExtraAccountMetaList(hookedMint)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const created = await connection.getAccountInfo(hookedExtraAccountMetaListPDA, "confirmed");
+    expect(created.owner.toBase58()).to.equal(program.programId.toBase58());
+    expect(getExtraAccountMetas(created)).to.have.length(extraAccountMetaCount);
+    expect(created.lamports).to.equal(
+      await connection.getMinimumBalanceForRentExemption(created.data.length)
+    );
+    const createdAuthority = await program.account.extraMetaAuthority.fetch(
+      hookedExtraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(createdAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+
+    // Second run is a no-op, the compute budget instruction only makes it a distinct transaction
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 200_000 }),
+        await ensureExtraAccountMetaList(hookedMint)
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const ensured = await connection.getAccountInfo(hookedExtraAccountMetaListPDA, "confirmed");
+    expect(ensured.data.equals(created.data)).to.be.true;
+    expect(ensured.lamports).to.equal(created.lamports);
+
+    // Fixed supply mints revoke their mint authority, re-running still succeeds afterwards
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        createSetAuthorityInstruction(
+          hookedMint,
+          wallet.publicKey,
+          AuthorityType.MintTokens,
+          null,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        ),
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 300_000 }),
+        await ensureExtraAccountMetaList(hookedMint)
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const revoked = await connection.getAccountInfo(hookedExtraAccountMetaListPDA, "confirmed");
+    expect(revoked.data.equals(created.data)).to.be.true;
+
+    // Running it against an already initialized mint leaves its list untouched
+    const before = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await ensureExtraAccountMetaList(mint.publicKey)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const after = await connection.getAccountInfo(extraAccountMetaListPDA, "confirmed");
+    expect(after.data.equals(before.data)).to.be.true;
+  });
+
+  // Only the mint authority or the transfer hook authority can become the authority over a
+  // mint's ExtraAccountMetaList account
+  it("Reject ExtraAccountMetaList creation from non-mint-authority", async () => {
+    const { mint: hookedMint } = await setupHookedMint(program.programId, null);
+    const impostor = Keypair.generate();
+
+    await expectTransactionError(
+      new Transaction().add(await initializeExtraAccountMetaList(hookedMint, impostor.publicKey)),
+      "NotSetupAuthority",
+      [wallet.payer, impostor]
+    );
+  });
+
+  // Mints whose mint authority was revoked, or is a multisig that can't sign,
+  // are set up by the authority of their TransferHook extension instead
+  it("Create ExtraAccountMetaList Account with the transfer hook authority", async () => {
+    const { mint: hookedMint } = await setupHookedMint(program.programId, null);
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        createSetAuthorityInstruction(
+          hookedMint,
+          wallet.publicKey,
+          AuthorityType.MintTokens,
+          null,
+          [],
+          TOKEN_2022_PROGRAM_ID
+        ),
+        await initializeExtraAccountMetaList(hookedMint, wallet.publicKey)
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+
+    const extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      extraMetaAuthorityPda(hookedMint),
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+  });
+
+  // Lists created before the ExtraMetaAuthority account existed can have their authority claimed
+  it("Claim ExtraMetaAuthority of an existing ExtraAccountMetaList", async () => {
+    const { mint: hookedMint } = await setupHookedMint(program.programId, null);
+    const hookedExtraMetaAuthorityPDA = extraMetaAuthorityPda(hookedMint);
+
+    // Nothing to claim before the list exists
+    await expectTransactionError(
+      new Transaction().add(await claimExtraMetaAuthority(hookedMint, wallet.publicKey)),
+      "InvalidExtraAccountMetaList"
+    );
+
+    // Create the list, then drop its ExtraMetaAuthority account to get a list without one
+    const closeTestExtraMetaAuthorityInstruction = await program.methods
+      .closeTestExtraMetaAuthority()
+      .accounts({
+        authority: wallet.publicKey,
+        extraMetaAuthority: hookedExtraMetaAuthorityPDA,
+        mint: hookedMint,
+      })
+      .instruction();
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(
+        await initializeExtraAccountMetaList(hookedMint, wallet.publicKey),
+        closeTestExtraMetaAuthorityInstruction
+      ),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    expect(await connection.getAccountInfo(hookedExtraMetaAuthorityPDA, "confirmed")).to.be.null;
+
+    const impostor = Keypair.generate();
+    await expectTransactionError(
+      new Transaction().add(await claimExtraMetaAuthority(hookedMint, impostor.publicKey)),
+      "NotSetupAuthority",
+      [wallet.payer, impostor]
+    );
+
+    await sendAndConfirmTransaction(
+      connection,
+      new Transaction().add(await claimExtraMetaAuthority(hookedMint, wallet.publicKey)),
+      [wallet.payer],
+      { commitment: "confirmed" }
+    );
+    const extraMetaAuthority = await program.account.extraMetaAuthority.fetch(
+      hookedExtraMetaAuthorityPDA,
+      "confirmed"
+    );
+    expect(extraMetaAuthority.authority.toBase58()).to.equal(wallet.publicKey.toBase58());
+
+    // The authority can only be claimed once, the compute budget instruction only makes
+    // it a distinct transaction
+    await expectTransactionError(
+      new Transaction().add(
+        ComputeBudgetProgram.setComputeUnitLimit({ units: 200_000 }),
+        await claimExtraMetaAuthority(hookedMint, wallet.publicKey)
+      ),
+      "already in use"
+    );
   });
 });